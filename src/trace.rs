@@ -1,53 +1,247 @@
 use anyhow::Result;
 use serde::Serialize;
-use tracing::{
-    span,
-    subscriber::{self, Subscriber},
-    Event, Id, Metadata,
+use tracing::{span, Event, Id};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer, Registry,
 };
-use tracing_core::span::Current;
 
 use std::{
     collections::HashMap,
-    fs,
     io::{Read, Seek},
     sync::{Arc, Mutex},
 };
 
 use super::{ParseError, Save, Types};
 
+/// Target used for the internal read/seek events so [`IoTraceLayer`] can pick them out of
+/// whatever else is flowing through the subscriber it's stacked under.
+const IO_EVENT_TARGET: &str = "uesave::trace::io";
+
 pub fn read<R: Read>(reader: &mut R) -> Result<Save, ParseError> {
-    CounterSubscriber::read(reader, Save::read)
+    let mut reader = TraceReader::new(reader);
+    Save::read(&mut reader)
 }
 
 pub fn read_with_types<R: Read>(reader: &mut R, types: &Types) -> Result<Save, ParseError> {
-    CounterSubscriber::read(reader, |reader| Save::read_with_types(reader, types))
+    let mut reader = TraceReader::new(reader);
+    Save::read_with_types(&mut reader, types)
+}
+
+/// Like [`read`], but installs an [`IoTraceLayer`] feeding into `collector` for the duration of
+/// the read and returns a [`TraceSummary`] aggregated from the resulting trace alongside the
+/// parsed `Save`. A subscriber is only swapped in here, when the caller actually wants a trace;
+/// plain `read`/`read_with_types` never touch the global subscriber.
+pub fn read_traced<R: Read>(
+    reader: &mut R,
+    collector: TraceCollector,
+) -> Result<(Save, TraceSummary), ParseError> {
+    traced(reader, collector, Save::read)
+}
+
+pub fn read_with_types_traced<R: Read>(
+    reader: &mut R,
+    types: &Types,
+    collector: TraceCollector,
+) -> Result<(Save, TraceSummary), ParseError> {
+    traced(reader, collector, |r| Save::read_with_types(r, types))
+}
+
+/// Shared by [`read_traced`] and [`read_with_types_traced`]: install a subscriber that feeds
+/// `collector` for the duration of `f`, then fold the resulting trace into a [`TraceSummary`].
+fn traced<'r, R: Read, F, T>(
+    reader: &'r mut R,
+    collector: TraceCollector,
+    f: F,
+) -> Result<(T, TraceSummary), ParseError>
+where
+    F: FnOnce(&mut TraceReader<&'r mut R>) -> Result<T, ParseError>,
+{
+    let subscriber = Registry::default().with(IoTraceLayer::new(collector.clone()));
+    let mut reader = TraceReader::new(reader);
+    let value = tracing::subscriber::with_default(subscriber, || f(&mut reader))?;
+    let tree = collector.take().expect("IoTraceLayer closes its root span before read returns");
+    Ok((value, TraceSummary::from_tree(&tree)))
+}
+
+/// Like [`read`], but also reports a byte-level [`Coverage`] map of the parse against a file of
+/// `file_len` bytes -- useful for spotting unparsed gaps and re-read regions when reverse
+/// engineering an unfamiliar save layout.
+pub fn read_with_coverage<R: Read>(
+    reader: &mut R,
+    file_len: u64,
+) -> Result<(Save, Coverage), ParseError> {
+    with_coverage(reader, file_len, Save::read)
+}
+
+pub fn read_with_types_and_coverage<R: Read>(
+    reader: &mut R,
+    types: &Types,
+    file_len: u64,
+) -> Result<(Save, Coverage), ParseError> {
+    with_coverage(reader, file_len, |r| Save::read_with_types(r, types))
+}
+
+/// Shared by [`read_with_coverage`] and [`read_with_types_and_coverage`]: run `f` over a
+/// coverage-tracking reader, then sweep the recorded ranges against `file_len`.
+fn with_coverage<'r, R: Read, F, T>(
+    reader: &'r mut R,
+    file_len: u64,
+    f: F,
+) -> Result<(T, Coverage), ParseError>
+where
+    F: FnOnce(&mut TraceReader<&'r mut R>) -> Result<T, ParseError>,
+{
+    let mut reader = TraceReader::new(reader);
+    let value = f(&mut reader)?;
+    let coverage = reader.coverage.lock().unwrap().report(file_len);
+    Ok((value, coverage))
 }
 
-struct TraceReader<R: Read> {
+struct TraceReader<R> {
     reader: R,
-    sub: CounterSubscriber,
+    coverage: Mutex<CoverageBuilder>,
 }
 
-impl<R: Read> TraceReader<R> {
-    fn new(reader: R, sub: CounterSubscriber) -> Self {
-        Self { reader, sub }
+impl<R> TraceReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            coverage: Mutex::new(CoverageBuilder::default()),
+        }
+    }
+
+    fn read_action(&self, size: usize) {
+        tracing::event!(target: IO_EVENT_TARGET, tracing::Level::TRACE, read = size as u64);
+        self.coverage.lock().unwrap().read(size as u64);
+    }
+
+    fn seek_action(&self, to: u64) {
+        tracing::event!(target: IO_EVENT_TARGET, tracing::Level::TRACE, seek = to);
+        self.coverage.lock().unwrap().seek(to);
     }
 }
 impl<R: Read + Seek> Seek for TraceReader<R> {
     fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
-        self.reader.seek(pos).map(|to| {
-            self.sub.seek_action(to);
-            to
-        })
+        self.reader.seek(pos).inspect(|&to| self.seek_action(to))
     }
 }
 impl<R: Read> Read for TraceReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.reader.read(buf).map(|s| {
-            self.sub.read_action(s);
-            s
-        })
+        self.reader.read(buf).inspect(|&s| self.read_action(s))
+    }
+}
+
+/// A half-open `[start, end)` byte range, used by [`Coverage`] to report gaps and overlaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Tracks the reader's running byte cursor (seeks set it absolutely, reads advance it by the
+/// number of bytes read) and every byte range actually read, so [`Self::report`] can diff that
+/// against the real file length once parsing is done.
+#[derive(Default)]
+struct CoverageBuilder {
+    cursor: u64,
+    reads: Vec<(u64, u64)>,
+}
+
+impl CoverageBuilder {
+    fn read(&mut self, size: u64) {
+        let start = self.cursor;
+        let end = start + size;
+        self.reads.push((start, end));
+        self.cursor = end;
+    }
+
+    fn seek(&mut self, to: u64) {
+        self.cursor = to;
+    }
+
+    /// Sweeps the recorded read ranges against `[0, file_len)`, classifying every byte as a gap
+    /// (never read), an overlap (read by more than one range), or ordinary single coverage.
+    fn report(&self, file_len: u64) -> Coverage {
+        let mut boundaries: Vec<u64> = self.reads.iter().flat_map(|&(s, e)| [s, e]).collect();
+        boundaries.push(0);
+        boundaries.push(file_len);
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut gaps = Vec::new();
+        let mut overlaps = Vec::new();
+        let mut bytes_read = 0u64;
+
+        for w in boundaries.windows(2) {
+            let (start, end) = (w[0], w[1]);
+            if start >= file_len {
+                continue;
+            }
+            let end = end.min(file_len);
+            let depth = self
+                .reads
+                .iter()
+                .filter(|&&(s, e)| s <= start && end <= e)
+                .count();
+            match depth {
+                0 => gaps.push(ByteRange { start, end }),
+                1 => bytes_read += end - start,
+                _ => {
+                    bytes_read += end - start;
+                    overlaps.push(ByteRange { start, end });
+                }
+            }
+        }
+
+        Coverage {
+            file_len,
+            bytes_read,
+            gaps,
+            overlaps,
+        }
+    }
+}
+
+/// Byte-level coverage of a parse against the file it read from: how much of the file was
+/// actually consumed, which `[start, end)` ranges were never touched (candidate unknown
+/// fields), and which were read more than once (usually a mis-sized struct re-syncing).
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    pub file_len: u64,
+    pub bytes_read: u64,
+    pub gaps: Vec<ByteRange>,
+    pub overlaps: Vec<ByteRange>,
+}
+
+impl Coverage {
+    /// Renders `data` (which must be the same file the coverage was computed over) as a 16-byte
+    /// hex dump with each byte tagged `.` if it falls in a gap, `+` if it falls in an overlap, or
+    /// ` ` otherwise -- enough to eyeball where a parser's understanding of a format has holes.
+    pub fn to_hex_dump(&self, data: &[u8]) -> String {
+        use std::fmt::Write;
+
+        let marker = |pos: u64| {
+            if self.gaps.iter().any(|g| g.start <= pos && pos < g.end) {
+                '.'
+            } else if self.overlaps.iter().any(|o| o.start <= pos && pos < o.end) {
+                '+'
+            } else {
+                ' '
+            }
+        };
+
+        let mut out = String::new();
+        for (row, chunk) in data.chunks(16).enumerate() {
+            let offset = (row * 16) as u64;
+            write!(out, "{:08x}  ", offset).unwrap();
+            for (i, byte) in chunk.iter().enumerate() {
+                write!(out, "{:02x}{} ", byte, marker(offset + i as u64)).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
     }
 }
 
@@ -72,135 +266,408 @@ impl<S> ReadSpan<S> {
     }
 }
 
-#[derive(Default)]
-struct CounterSubscriberInner {
-    last_id: u64,
-    root_span: Option<Id>,
-    spans: HashMap<Id, ReadSpan<Id>>,
-    metadata: HashMap<Id, &'static Metadata<'static>>,
-    stack: Vec<Id>,
-}
-
 #[derive(Debug, Serialize)]
 #[repr(transparent)]
-struct TreeSpan(ReadSpan<TreeSpan>);
+pub struct TreeSpan(ReadSpan<TreeSpan>);
 impl TreeSpan {
-    fn into_tree(id: Id, spans: &mut HashMap<Id, ReadSpan<Id>>) -> Self {
-        let read_span = spans.remove(&id).unwrap();
-        Self(ReadSpan {
-            name: read_span.name,
-            actions: read_span
-                .actions
-                .into_iter()
-                .map(|a| match a {
-                    Action::Read(i) => Action::Read(i),
-                    Action::Seek(i) => Action::Seek(i),
-                    Action::Span(id) => Action::Span(Self::into_tree(id, spans)),
-                })
-                .collect(),
-        })
+    /// Render this span tree as a [Firefox Profiler processed profile][1], using cumulative
+    /// bytes read (rather than wall-clock time) as the sample axis, so the result can be opened
+    /// directly in https://profiler.firefox.com and explored as a flamegraph.
+    ///
+    /// [1]: https://github.com/firefox-devtools/profiler/blob/main/docs-developer/processed-profile-format.md
+    pub fn to_firefox_profile(&self) -> FirefoxProfile {
+        let mut builder = FirefoxProfileBuilder::default();
+        let mut offset = 0u64;
+        let root = builder.visit(&self.0, None, &mut offset);
+        builder.build(root)
     }
 }
 
-impl Drop for CounterSubscriberInner {
-    fn drop(&mut self) {
-        let tree = TreeSpan::into_tree(self.root_span.as_ref().cloned().unwrap(), &mut self.spans);
-        let json = serde_json::to_string(&tree).unwrap();
-        fs::write("trace.json", json).unwrap();
+#[derive(Debug, Serialize)]
+pub struct FirefoxProfile {
+    meta: FirefoxProfileMeta,
+    threads: Vec<FirefoxThread>,
+}
+
+#[derive(Debug, Serialize)]
+struct FirefoxProfileMeta {
+    interval: f64,
+    #[serde(rename = "startTime")]
+    start_time: f64,
+    #[serde(rename = "processType")]
+    process_type: u32,
+    product: &'static str,
+    version: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct FirefoxThread {
+    name: &'static str,
+    #[serde(rename = "processType")]
+    process_type: &'static str,
+    pid: u32,
+    tid: u32,
+    #[serde(rename = "stringArray")]
+    string_array: Vec<String>,
+    #[serde(rename = "funcTable")]
+    func_table: FuncTable,
+    #[serde(rename = "frameTable")]
+    frame_table: FrameTable,
+    #[serde(rename = "stackTable")]
+    stack_table: StackTable,
+    samples: SampleTable,
+}
+
+#[derive(Debug, Serialize)]
+struct FuncTable {
+    length: usize,
+    name: Vec<usize>,
+    #[serde(rename = "isJS")]
+    is_js: Vec<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct FrameTable {
+    length: usize,
+    func: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct StackTable {
+    length: usize,
+    prefix: Vec<Option<usize>>,
+    frame: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct SampleTable {
+    length: usize,
+    stack: Vec<Option<usize>>,
+    time: Vec<f64>,
+    weight: Vec<i64>,
+    #[serde(rename = "weightType")]
+    weight_type: &'static str,
+}
+
+/// Accumulates deduplicated funcs/frames, plus one stack row per visited span (stack rows are
+/// not deduplicated), while walking a [`TreeSpan`] depth-first, turning each `Action::Read` into
+/// a sample weighted by the bytes it consumed.
+#[derive(Default)]
+struct FirefoxProfileBuilder {
+    string_array: Vec<String>,
+    string_index: HashMap<String, usize>,
+    func_names: Vec<usize>,
+    func_index: HashMap<&'static str, usize>,
+    stack_prefix: Vec<Option<usize>>,
+    stack_frame: Vec<usize>,
+    sample_stack: Vec<Option<usize>>,
+    sample_time: Vec<f64>,
+    sample_weight: Vec<i64>,
+}
+
+impl FirefoxProfileBuilder {
+    fn intern_string(&mut self, s: &str) -> usize {
+        if let Some(&i) = self.string_index.get(s) {
+            return i;
+        }
+        let i = self.string_array.len();
+        self.string_array.push(s.to_string());
+        self.string_index.insert(s.to_string(), i);
+        i
+    }
+
+    /// One func (and one 1:1 frame) per distinct span name.
+    fn func_for_name(&mut self, name: &'static str) -> usize {
+        if let Some(&i) = self.func_index.get(name) {
+            return i;
+        }
+        let string_idx = self.intern_string(name);
+        let i = self.func_names.len();
+        self.func_names.push(string_idx);
+        self.func_index.insert(name, i);
+        i
+    }
+
+    fn push_stack(&mut self, prefix: Option<usize>, frame: usize) -> usize {
+        let i = self.stack_frame.len();
+        self.stack_prefix.push(prefix);
+        self.stack_frame.push(frame);
+        i
+    }
+
+    fn visit(&mut self, span: &ReadSpan<TreeSpan>, parent: Option<usize>, offset: &mut u64) -> usize {
+        let func = self.func_for_name(span.name);
+        let stack = self.push_stack(parent, func);
+        for action in &span.actions {
+            match action {
+                Action::Read(n) => {
+                    self.sample_stack.push(Some(stack));
+                    self.sample_time.push(*offset as f64);
+                    self.sample_weight.push(*n as i64);
+                    *offset += *n as u64;
+                }
+                // Seeks don't consume bytes, so they're recorded as zero-weight samples rather
+                // than widening the flamegraph.
+                Action::Seek(_) => {
+                    self.sample_stack.push(Some(stack));
+                    self.sample_time.push(*offset as f64);
+                    self.sample_weight.push(0);
+                }
+                Action::Span(child) => {
+                    self.visit(&child.0, Some(stack), offset);
+                }
+            }
+        }
+        stack
+    }
+
+    fn build(self, _root_stack: usize) -> FirefoxProfile {
+        let func_count = self.func_names.len();
+        FirefoxProfile {
+            meta: FirefoxProfileMeta {
+                interval: 1.0,
+                start_time: 0.0,
+                process_type: 0,
+                product: "uesave",
+                version: 24,
+            },
+            threads: vec![FirefoxThread {
+                name: "parse",
+                process_type: "default",
+                pid: 0,
+                tid: 0,
+                string_array: self.string_array,
+                func_table: FuncTable {
+                    length: func_count,
+                    name: self.func_names,
+                    is_js: vec![false; func_count],
+                },
+                frame_table: FrameTable {
+                    length: func_count,
+                    func: (0..func_count).collect(),
+                },
+                stack_table: StackTable {
+                    length: self.stack_frame.len(),
+                    prefix: self.stack_prefix,
+                    frame: self.stack_frame,
+                },
+                samples: SampleTable {
+                    length: self.sample_stack.len(),
+                    stack: self.sample_stack,
+                    time: self.sample_time,
+                    weight: self.sample_weight,
+                    weight_type: "bytes",
+                },
+            }],
+        }
     }
 }
 
+/// Where a traced read's finished [`TreeSpan`] ends up. Cloned into the [`IoTraceLayer`] that
+/// feeds it, and kept by the caller to retrieve the result once the read is done.
 #[derive(Clone, Default)]
-struct CounterSubscriber {
-    inner: Arc<Mutex<CounterSubscriberInner>>,
-}
-impl CounterSubscriber {
-    pub fn read<'t, 'r: 't, R: Read + 'r, F, T>(reader: &'r mut R, f: F) -> T
-    where
-        F: Fn(&mut TraceReader<&'r mut R>) -> T,
-    {
-        let sub = Self::default();
-        let mut reader = TraceReader::new(reader, sub.clone());
-        tracing::subscriber::with_default(sub, || f(&mut reader))
+pub struct TraceCollector(Arc<Mutex<Option<TreeSpan>>>);
+
+impl TraceCollector {
+    pub fn new() -> Self {
+        Self::default()
     }
-    fn read_action(&self, size: usize) {
-        let mut lock = self.inner.lock().unwrap();
-        let current = lock.stack.last().cloned().unwrap();
-        lock.spans
-            .get_mut(&current)
-            .unwrap()
-            .actions
-            .push(Action::Read(size));
+
+    /// Takes the completed trace tree, if the traced read it was passed to has finished.
+    pub fn take(&self) -> Option<TreeSpan> {
+        self.0.lock().unwrap().take()
     }
-    fn seek_action(&self, to: u64) {
-        let mut lock = self.inner.lock().unwrap();
-        let current = lock.stack.last().cloned().unwrap();
-        lock.spans
-            .get_mut(&current)
-            .unwrap()
-            .actions
-            .push(Action::Seek(to as usize));
-    }
-}
-
-impl Subscriber for CounterSubscriber {
-    fn register_callsite(&self, _meta: &Metadata<'_>) -> subscriber::Interest {
-        subscriber::Interest::always()
-    }
-
-    fn new_span(&self, new_span: &span::Attributes<'_>) -> Id {
-        let mut lock = self.inner.lock().unwrap();
-
-        let metadata = new_span.metadata();
-        let name = metadata.name();
-        lock.last_id += 1;
-        let id = lock.last_id;
-        let id = Id::from_u64(id);
-
-        lock.spans.insert(id.clone(), ReadSpan::new(name));
-        lock.metadata.insert(id.clone(), metadata);
-        assert_eq!(new_span.parent(), None);
-        assert!(new_span.is_contextual());
-        // TODO set root here if new_span.is_root()?
-        id
-    }
-    fn try_close(&self, _id: Id) -> bool {
-        true
-    }
-    fn current_span(&self) -> Current {
-        let lock = self.inner.lock().unwrap();
-        if let Some(id) = lock.stack.last() {
-            let metadata = lock.metadata[id];
-            Current::new(id.clone(), metadata)
-        } else {
-            Current::none()
+}
+
+/// Byte/seek accounting for a single parse stage (a distinct span `name`), aggregated from a
+/// [`TreeSpan`] by [`TraceSummary::from_tree`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StageStats {
+    pub bytes_read: u64,
+    pub read_count: u64,
+    pub seek_count: u64,
+    /// Total bytes seeks in this stage moved the cursor backward. A mis-sized struct that
+    /// over-reads and then seeks back to resync shows up here.
+    pub net_backward_seek_bytes: u64,
+}
+
+/// Per-stage read/seek accounting over a whole parse, grouped by span name so a caller can ask
+/// "which stage read the most bytes" or "how many backward seeks happened" without re-parsing
+/// the raw trace tree themselves.
+#[derive(Debug, Default)]
+pub struct TraceSummary {
+    pub per_stage: HashMap<&'static str, StageStats>,
+}
+
+impl TraceSummary {
+    pub fn from_tree(tree: &TreeSpan) -> Self {
+        let mut summary = Self::default();
+        let mut cursor = 0u64;
+        summary.visit(&tree.0, &mut cursor);
+        summary
+    }
+
+    fn visit(&mut self, span: &ReadSpan<TreeSpan>, cursor: &mut u64) {
+        for action in &span.actions {
+            match action {
+                Action::Read(n) => {
+                    let stats = self.stage_mut(span.name);
+                    stats.bytes_read += *n as u64;
+                    stats.read_count += 1;
+                    *cursor += *n as u64;
+                }
+                Action::Seek(to) => {
+                    let to = *to as u64;
+                    let stats = self.stage_mut(span.name);
+                    stats.seek_count += 1;
+                    if to < *cursor {
+                        stats.net_backward_seek_bytes += *cursor - to;
+                    }
+                    *cursor = to;
+                }
+                // Recursing while holding a borrow of `self.per_stage` here would conflict with
+                // the child's own `stage_mut` calls, so look the parent's entry up fresh in the
+                // other two arms instead of hoisting it once above the loop.
+                Action::Span(child) => self.visit(&child.0, cursor),
+            }
         }
     }
 
-    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
-    fn record(&self, _: &Id, _values: &span::Record<'_>) {}
-    fn event(&self, _event: &Event<'_>) {}
+    fn stage_mut(&mut self, name: &'static str) -> &mut StageStats {
+        self.per_stage.entry(name).or_default()
+    }
+
+    /// Render the summary in the standard Prometheus text exposition format, so a host
+    /// application can scrape parse metrics without depending on the JSON trace dump.
+    pub fn to_prometheus_text(&self) -> String {
+        let mut stages: Vec<_> = self.per_stage.keys().copied().collect();
+        stages.sort_unstable();
+
+        let mut out = String::new();
+        type Metric = (&'static str, &'static str, fn(&StageStats) -> u64);
+        let metrics: &[Metric] = &[
+            (
+                "uesave_bytes_read_total",
+                "Total bytes read per parse stage.",
+                |s| s.bytes_read,
+            ),
+            (
+                "uesave_reads_total",
+                "Total read calls per parse stage.",
+                |s| s.read_count,
+            ),
+            (
+                "uesave_seeks_total",
+                "Total seek calls per parse stage.",
+                |s| s.seek_count,
+            ),
+            (
+                "uesave_backward_seek_bytes_total",
+                "Net bytes moved backward by seeks per parse stage.",
+                |s| s.net_backward_seek_bytes,
+            ),
+        ];
 
-    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
-        true
+        for (metric, help, value) in metrics {
+            out.push_str(&format!("# HELP {metric} {help}\n"));
+            out.push_str(&format!("# TYPE {metric} counter\n"));
+            for stage in &stages {
+                let stats = &self.per_stage[stage];
+                out.push_str(&format!("{metric}{{stage=\"{stage}\"}} {}\n", value(stats)));
+            }
+        }
+        out
     }
+}
+
+/// Per-span scratch data the layer attaches via the registry's span extensions; holds the
+/// in-progress [`ReadSpan`] for a span until it closes and gets folded into its parent (or, for
+/// the outermost span of a traced read, handed off to the [`TraceCollector`]).
+struct SpanData(ReadSpan<TreeSpan>);
+
+/// A [`tracing_subscriber::Layer`] that turns `Read`/`Seek` activity on a [`TraceReader`] into a
+/// tree of spans, without taking over the whole subscriber. Stack it under a `Registry` alongside
+/// whatever other layers (`fmt`, `EnvFilter`, ...) an embedding application already uses:
+///
+/// ```ignore
+/// let collector = TraceCollector::new();
+/// let subscriber = tracing_subscriber::registry()
+///     .with(tracing_subscriber::fmt::layer())
+///     .with(IoTraceLayer::new(collector.clone()));
+/// ```
+pub struct IoTraceLayer {
+    collector: TraceCollector,
+}
 
-    fn enter(&self, span: &Id) {
-        let mut lock = self.inner.lock().unwrap();
-        if let Some(current) = lock.stack.last().cloned() {
-            lock.spans
-                .get_mut(&current)
-                .unwrap()
-                .actions
-                .push(Action::Span(span.clone()));
-        } else {
-            lock.root_span = Some(span.clone());
+impl IoTraceLayer {
+    pub fn new(collector: TraceCollector) -> Self {
+        Self { collector }
+    }
+}
+
+#[derive(Default)]
+struct IoEventVisitor {
+    read: Option<u64>,
+    seek: Option<u64>,
+}
+impl tracing::field::Visit for IoEventVisitor {
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        match field.name() {
+            "read" => self.read = Some(value),
+            "seek" => self.seek = Some(value),
+            _ => {}
         }
-        lock.stack.push(span.clone());
     }
-    fn exit(&self, span: &Id) {
-        let mut lock = self.inner.lock().unwrap();
-        assert_eq!(&lock.stack.pop().unwrap(), span);
+    fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+}
+
+impl<S> Layer<S> for IoTraceLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span must exist in on_new_span");
+        span.extensions_mut()
+            .insert(SpanData(ReadSpan::new(attrs.metadata().name())));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != IO_EVENT_TARGET {
+            return;
+        }
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut visitor = IoEventVisitor::default();
+        event.record(&mut visitor);
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else {
+            return;
+        };
+        if let Some(n) = visitor.read {
+            data.0.actions.push(Action::Read(n as usize));
+        } else if let Some(to) = visitor.seek {
+            data.0.actions.push(Action::Seek(to as usize));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("span must exist in on_close");
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        let tree = TreeSpan(data.0);
+        if let Some(parent) = span.parent() {
+            let mut extensions = parent.extensions_mut();
+            if let Some(parent_data) = extensions.get_mut::<SpanData>() {
+                parent_data.0.actions.push(Action::Span(tree));
+                return;
+            }
+        }
+        // No parent, or a parent without `SpanData` attached -- this is as far up as the traced
+        // tree goes. Every span this layer sees gets `SpanData` in `on_new_span`, so any
+        // enclosing span (including an embedding application's own) becomes part of the tree.
+        *self.collector.0.lock().unwrap() = Some(tree);
     }
 }
 
@@ -208,17 +675,18 @@ impl Subscriber for CounterSubscriber {
 mod test {
     use byteorder::{ReadBytesExt, LE};
     use tracing::instrument;
+    use tracing_subscriber::layer::SubscriberExt;
 
     use super::*;
 
     #[instrument(name = "read_nested_stuff", skip_all)]
-    fn read_nested_stuff<R: Read + Seek>(reader: &mut R) -> Result<()> {
+    fn read_nested_stuff<R: Read + Seek>(reader: &mut R) -> Result<(), ParseError> {
         let _a = reader.read_u32::<LE>()?;
         Ok(())
     }
 
     #[instrument(name = "read_stuff", skip_all)]
-    fn read_stuff<R: Read + Seek>(reader: &mut R) -> Result<()> {
+    fn read_stuff<R: Read + Seek>(reader: &mut R) -> Result<(), ParseError> {
         let _a = reader.read_u8()?;
         read_nested_stuff(reader)?;
         reader.seek(std::io::SeekFrom::Current(-1))?;
@@ -228,10 +696,118 @@ mod test {
 
     #[test]
     fn test_trace() -> Result<()> {
-        let mut reader = std::io::Cursor::new(vec![1, 2, 3, 4, 5, 6]);
+        let collector = TraceCollector::new();
+        let subscriber = Registry::default().with(IoTraceLayer::new(collector.clone()));
+        let mut reader = TraceReader::new(std::io::Cursor::new(vec![1, 2, 3, 4, 5, 6]));
+
+        tracing::subscriber::with_default(subscriber, || read_stuff(&mut reader))?;
+
+        let tree = collector.take().expect("traced read should produce a tree");
+        assert_eq!(tree.0.name, "read_stuff");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_traced_entry_point() -> Result<()> {
+        let collector = TraceCollector::new();
+        let mut cursor = std::io::Cursor::new(vec![1, 2, 3, 4, 5, 6]);
+
+        let ((), summary) = traced(&mut cursor, collector, read_stuff)?;
+
+        assert_eq!(summary.per_stage["read_stuff"].read_count, 2);
+        assert_eq!(summary.per_stage["read_nested_stuff"].read_count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_coverage_entry_point() -> Result<()> {
+        let mut cursor = std::io::Cursor::new(vec![1, 2, 3, 4, 5, 6]);
 
-        CounterSubscriber::read(&mut reader, read_stuff)?;
+        let ((), coverage) = with_coverage(&mut cursor, 6, read_stuff)?;
+
+        assert_eq!(coverage.bytes_read, 5);
+        assert_eq!(coverage.gaps, vec![ByteRange { start: 5, end: 6 }]);
 
         Ok(())
     }
+
+    #[test]
+    fn test_coverage() {
+        let mut builder = CoverageBuilder::default();
+        builder.read(1); // [0, 1)
+        builder.read(4); // [1, 5)
+        builder.seek(4); // back up, re-reading the last byte of the previous read
+        builder.read(1); // [4, 5)
+
+        let coverage = builder.report(6);
+
+        assert_eq!(coverage.bytes_read, 5);
+        assert_eq!(coverage.gaps, vec![ByteRange { start: 5, end: 6 }]);
+        assert_eq!(coverage.overlaps, vec![ByteRange { start: 4, end: 5 }]);
+
+        let dump = coverage.to_hex_dump(&[1, 2, 3, 4, 5, 6]);
+        assert!(dump.contains("05+"));
+        assert!(dump.contains("06. "));
+    }
+
+    #[test]
+    fn test_trace_summary() {
+        let tree = TreeSpan(ReadSpan {
+            name: "read_stuff",
+            actions: vec![
+                Action::Read(1),
+                Action::Span(TreeSpan(ReadSpan {
+                    name: "read_nested_stuff",
+                    actions: vec![Action::Read(4)],
+                })),
+                Action::Seek(4),
+                Action::Read(1),
+            ],
+        });
+
+        let summary = TraceSummary::from_tree(&tree);
+
+        let outer = summary.per_stage["read_stuff"];
+        assert_eq!(outer.bytes_read, 2);
+        assert_eq!(outer.read_count, 2);
+        assert_eq!(outer.seek_count, 1);
+        assert_eq!(outer.net_backward_seek_bytes, 1);
+
+        let inner = summary.per_stage["read_nested_stuff"];
+        assert_eq!(inner.bytes_read, 4);
+        assert_eq!(inner.seek_count, 0);
+
+        let text = summary.to_prometheus_text();
+        assert!(text.contains("uesave_bytes_read_total{stage=\"read_stuff\"} 2"));
+        assert!(text.contains("uesave_backward_seek_bytes_total{stage=\"read_stuff\"} 1"));
+    }
+
+    #[test]
+    fn test_to_firefox_profile() {
+        let tree = TreeSpan(ReadSpan {
+            name: "read_stuff",
+            actions: vec![
+                Action::Read(1),
+                Action::Span(TreeSpan(ReadSpan {
+                    name: "read_nested_stuff",
+                    actions: vec![Action::Read(4)],
+                })),
+                Action::Seek(0),
+                Action::Read(1),
+            ],
+        });
+
+        let profile = tree.to_firefox_profile();
+        let thread = &profile.threads[0];
+
+        assert_eq!(thread.func_table.length, 2);
+        assert_eq!(thread.string_array, vec!["read_stuff", "read_nested_stuff"]);
+        assert_eq!(thread.samples.weight, vec![1, 4, 0, 1]);
+        assert_eq!(thread.samples.time, vec![0.0, 1.0, 5.0, 5.0]);
+        // Per the processed-profile-format spec, `weightType` belongs on each thread's samples
+        // table, not the profile's top-level meta -- the importer looks for it there.
+        assert_eq!(thread.samples.weight_type, "bytes");
+    }
 }